@@ -0,0 +1,47 @@
+//
+// Copyright (c) 2016 KAMADA Ken'ichi.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+// 1. Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+// 2. Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in the
+//    documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED.  IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE
+// FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+// DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS
+// OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION)
+// HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT
+// LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY
+// OUT OF THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF
+// SUCH DAMAGE.
+//
+
+use tag_priv::{Context, Tag};
+
+pub const ExifIFDPointer: Tag = Tag(Context::Tiff, 0x8769);
+pub const GPSInfoIFDPointer: Tag = Tag(Context::Tiff, 0x8825);
+pub const InteropIFDPointer: Tag = Tag(Context::Exif, 0xa005);
+pub const MakerNote: Tag = Tag(Context::Exif, 0x927c);
+pub const StripOffsets: Tag = Tag(Context::Tiff, 0x0111);
+pub const StripByteCounts: Tag = Tag(Context::Tiff, 0x0117);
+pub const JPEGInterchangeFormat: Tag = Tag(Context::Tiff, 0x0201);
+pub const JPEGInterchangeFormatLength: Tag = Tag(Context::Tiff, 0x0202);
+
+/// Returns a human-readable name for the IFD a tag's context refers to.
+pub fn context_name(ctx: Context) -> &'static str {
+    match ctx {
+        Context::Tiff => "TIFF",
+        Context::Exif => "Exif",
+        Context::Gps => "GPS",
+        Context::Interop => "Interop",
+        Context::MakerNote => "MakerNote",
+    }
+}
@@ -24,6 +24,8 @@
 // SUCH DAMAGE.
 //
 
+use std::cmp;
+use std::collections::HashMap;
 use std::fmt;
 
 use endian::{Endian, BigEndian, LittleEndian};
@@ -41,50 +43,149 @@ const TIFF_FORTY_TWO: u16 = 0x002a;
 pub const TIFF_BE_SIG: [u8; 4] = [0x4d, 0x4d, 0x00, 0x2a];
 pub const TIFF_LE_SIG: [u8; 4] = [0x49, 0x49, 0x2a, 0x00];
 
+/// An index of an IFD in a TIFF stream.
+///
+/// `In(0)` is the primary (0th) IFD, and `In(1)` is the thumbnail (1st)
+/// IFD.  This generalizes the old primary-vs-thumbnail distinction so
+/// that IFDs beyond the thumbnail can be addressed as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct In(pub u16);
+
+impl In {
+    /// The primary (0th) IFD.
+    pub const PRIMARY: In = In(0);
+    /// The thumbnail (1st) IFD.
+    pub const THUMBNAIL: In = In(1);
+}
+
 /// A TIFF field.
 #[derive(Debug)]
 pub struct Field<'a> {
     /// The tag of this field.
     pub tag: Tag,
-    /// False for the primary image and true for the thumbnail.
-    pub thumbnail: bool,
+    /// The IFD that contains this field.
+    pub ifd_num: In,
     /// The value of this field.
     pub value: Value<'a>,
 }
 
+impl<'a> Field<'a> {
+    /// Equivalent to `self.ifd_num == In::THUMBNAIL`.
+    #[deprecated(note = "use `ifd_num` and compare it against \
+                          `In::THUMBNAIL` instead")]
+    pub fn thumbnail(&self) -> bool {
+        self.ifd_num == In::THUMBNAIL
+    }
+}
+
+/// The parsed content of a TIFF/Exif stream.
+#[derive(Debug)]
+pub struct Exif<'a> {
+    fields: Vec<Field<'a>>,
+    little_endian: bool,
+    index: HashMap<(Tag, In), usize>,
+}
+
+impl<'a> Exif<'a> {
+    fn new(fields: Vec<Field<'a>>, index: HashMap<(Tag, In), usize>,
+           little_endian: bool) -> Exif<'a> {
+        Exif { fields: fields, little_endian: little_endian, index: index }
+    }
+
+    /// Returns all the parsed fields.
+    pub fn fields(&self) -> &[Field<'a>] {
+        &self.fields
+    }
+
+    /// Returns true if the data is little endian.
+    pub fn little_endian(&self) -> bool {
+        self.little_endian
+    }
+
+    /// Returns the field with the given tag in the given IFD, if any.
+    /// This is an O(1) lookup backed by a `HashMap` built while parsing.
+    pub fn get_field(&self, tag: Tag, ifd: In) -> Option<&Field<'a>> {
+        self.index.get(&(tag, ifd)).map(|&i| &self.fields[i])
+    }
+
+    /// Decodes vendor-specific entries out of this `Exif`'s `MakerNote`
+    /// field, if any, adding them as `Context::MakerNote` fields.  See
+    /// the free function `parse_makernote` for details; this is the
+    /// supported way to invoke it against a `parse_exif` result, since
+    /// `fields` and `index` are not otherwise reachable from outside
+    /// this module.
+    pub fn parse_makernote(&mut self, data: &'a [u8]) -> Result<(), Error> {
+        parse_makernote(&mut self.fields, &mut self.index, data,
+                         self.little_endian)
+    }
+}
+
 /// Parse the Exif attributes in the TIFF format.
 ///
-/// Returns a Vec of Exif fields and a bool.
-/// The boolean value is true if the data is little endian.
-/// If an error occurred, `exif::Error` is returned.
-pub fn parse_exif(data: &[u8]) -> Result<(Vec<Field>, bool), Error> {
+/// Returns the parsed `Exif`, from which fields can be looked up by
+/// tag and IFD.  If an error occurred, `exif::Error` is returned.
+pub fn parse_exif(data: &[u8]) -> Result<Exif, Error> {
     // Check the byte order and call the real parser.
     if data.len() < 8 {
         return Err(Error::InvalidFormat("Truncated TIFF header"));
     }
     match BigEndian::loadu16(data, 0) {
-        TIFF_BE => parse_exif_sub::<BigEndian>(data).map(|v| (v, false)),
-        TIFF_LE => parse_exif_sub::<LittleEndian>(data).map(|v| (v, true)),
+        TIFF_BE => parse_exif_sub::<BigEndian>(data)
+            .map(|(f, i)| Exif::new(f, i, false)),
+        TIFF_LE => parse_exif_sub::<LittleEndian>(data)
+            .map(|(f, i)| Exif::new(f, i, true)),
         _ => Err(Error::InvalidFormat("Invalid TIFF byte order")),
     }
 }
 
 fn parse_exif_sub<E>(data: &[u8])
-                     -> Result<Vec<Field>, Error> where E: Endian {
+                     -> Result<(Vec<Field>, HashMap<(Tag, In), usize>), Error>
+                     where E: Endian {
     // Parse the rest of the header (42 and the IFD offset).
     if E::loadu16(data, 2) != TIFF_FORTY_TWO {
         return Err(Error::InvalidFormat("Invalid forty two"));
     }
     let ifd_offset = E::loadu32(data, 4) as usize;
     let mut fields = Vec::new();
-    try!(parse_ifd::<E>(&mut fields, data, ifd_offset, Context::Tiff, false));
-    Ok(fields)
+    let mut index = HashMap::new();
+    try!(parse_ifd_chain::<E>(&mut fields, &mut index, data, ifd_offset));
+    Ok((fields, index))
+}
+
+// Follow the chain of top-level IFDs starting at `first_offset`: the
+// 0th IFD, then the 1st (thumbnail) IFD and any further IFDs some
+// TIFF-based formats (multi-page TIFF, some raw containers) chain
+// after it, assigning each an incrementing `In` number.  Already-visited
+// offsets are tracked to reject a chain that loops back on itself.
+fn parse_ifd_chain<'a, E>(fields: &mut Vec<Field<'a>>,
+                          index: &mut HashMap<(Tag, In), usize>,
+                          data: &'a [u8], first_offset: usize)
+                          -> Result<(), Error> where E: Endian {
+    let mut visited = Vec::new();
+    let mut offset = first_offset;
+    let mut ifd_num = In::PRIMARY;
+    loop {
+        if visited.contains(&offset) {
+            return Err(Error::InvalidFormat("IFD loop"));
+        }
+        visited.push(offset);
+        match try!(parse_ifd::<E>(fields, index, data, offset,
+                                   Context::Tiff, ifd_num)) {
+            Some(next_offset) => {
+                offset = next_offset;
+                ifd_num = In(ifd_num.0 + 1);
+            },
+            None => return Ok(()),
+        }
+    }
 }
 
-// Parse IFD [EXIF23 4.6.2].
-fn parse_ifd<'a, E>(fields: &mut Vec<Field<'a>>, data: &'a [u8],
-                    offset: usize, ctx: Context, thumbnail: bool)
-                    -> Result<(), Error> where E: Endian {
+// Parse IFD [EXIF23 4.6.2].  Returns the offset of the next IFD in the
+// chain, if this IFD has one.
+fn parse_ifd<'a, E>(fields: &mut Vec<Field<'a>>,
+                    index: &mut HashMap<(Tag, In), usize>, data: &'a [u8],
+                    offset: usize, ctx: Context, ifd_num: In)
+                    -> Result<Option<usize>, Error> where E: Endian {
     // Count (the number of the entries).
     if data.len() < offset || data.len() - offset < 2 {
         return Err(Error::InvalidFormat("Truncated IFD count"));
@@ -121,13 +222,19 @@ fn parse_ifd<'a, E>(fields: &mut Vec<Field<'a>>, data: &'a [u8],
         let tag = Tag(ctx, tag);
         match tag {
             tag::ExifIFDPointer => try!(parse_child_ifd::<E>(
-                fields, data, &val, Context::Exif, thumbnail)),
+                fields, index, data, &val, Context::Exif, ifd_num)),
             tag::GPSInfoIFDPointer => try!(parse_child_ifd::<E>(
-                fields, data, &val, Context::Gps, thumbnail)),
+                fields, index, data, &val, Context::Gps, ifd_num)),
             tag::InteropIFDPointer => try!(parse_child_ifd::<E>(
-                fields, data, &val, Context::Interop, thumbnail)),
-            _ => fields.push(Field { tag: tag, thumbnail: thumbnail,
-                                     value: val }),
+                fields, index, data, &val, Context::Interop, ifd_num)),
+            _ => {
+                // On a duplicate tag, keep the first occurrence in the
+                // index so `get_field` agrees with a linear scan over
+                // `fields()`, which always sees the first one too.
+                index.entry((tag, ifd_num)).or_insert(fields.len());
+                fields.push(Field { tag: tag, ifd_num: ifd_num,
+                                     value: val });
+            },
         }
     }
 
@@ -137,29 +244,532 @@ fn parse_ifd<'a, E>(fields: &mut Vec<Field<'a>>, data: &'a [u8],
     }
     let next_ifd_offset = E::loadu32(data, offset + 2 + count * 12) as usize;
     if next_ifd_offset == 0 {
-        return Ok(());
+        return Ok(None);
     }
-    if ctx != Context::Tiff || thumbnail {
+    if ctx != Context::Tiff {
         return Err(Error::InvalidFormat("Unexpected next IFD"));
     }
-    parse_ifd::<E>(fields, data, next_ifd_offset, Context::Tiff, true)
+    Ok(Some(next_ifd_offset))
 }
 
-fn parse_child_ifd<'a, E>(fields: &mut Vec<Field<'a>>, data: &'a [u8],
-                          pointer: &Value, ctx: Context, thumbnail: bool)
-                          -> Result<(), Error> where E: Endian {
+fn parse_child_ifd<'a, E>(fields: &mut Vec<Field<'a>>,
+                          index: &mut HashMap<(Tag, In), usize>,
+                          data: &'a [u8], pointer: &Value, ctx: Context,
+                          ifd_num: In) -> Result<(), Error> where E: Endian {
     // A pointer field has type == LONG and count == 1, so the
     // value (IFD offset) must be embedded in the "value offset"
     // element of the field.
     let ofs = try!(pointer.get_uint(0).ok_or(
         Error::InvalidFormat("Invalid pointer"))) as usize;
-    parse_ifd::<E>(fields, data, ofs, ctx, thumbnail)
+    // A sub-IFD's context is never Tiff, so `parse_ifd` always returns
+    // `None` here or fails; a genuine next-IFD chain never occurs.
+    try!(parse_ifd::<E>(fields, index, data, ofs, ctx, ifd_num));
+    Ok(())
+}
+
+// Recognized MakerNote signature prefixes.  Whether a vendor's value
+// offsets are relative to the TIFF header (like any other IFD) or to
+// a header inside the MakerNote blob itself is a property of the vendor's
+// format, not something derivable from the data, so it is hard-coded
+// per signature below.
+const MAKERNOTE_OLYMPUS_SIG: &'static [u8] = b"OLYMP\0";
+const MAKERNOTE_NIKON_SIG: &'static [u8] = b"Nikon\0";
+
+/// Decodes vendor-specific entries out of a `MakerNote` field into
+/// `Context::MakerNote` fields, if its content starts with a
+/// recognized signature.
+///
+/// An opt-in pass over the `fields`/`index` produced by `parse_exif`.
+/// Most callers should use `Exif::parse_makernote` instead, which
+/// applies this to an `Exif`'s own fields and index in place.  Leaves
+/// `fields` and `index` untouched if there is no `MakerNote` field in
+/// the primary IFD or its signature is not recognized.
+pub fn parse_makernote<'a>(fields: &mut Vec<Field<'a>>,
+                           index: &mut HashMap<(Tag, In), usize>,
+                           data: &'a [u8], little_endian: bool)
+                           -> Result<(), Error> {
+    let note = fields.iter()
+        .find(|f| f.tag == tag::MakerNote && f.ifd_num == In::PRIMARY)
+        .map(|f| &f.value);
+    let (note_data, note_offset) = match note {
+        Some(&Value::Undefined(d, ofs)) => (d, ofs as usize),
+        _ => return Ok(()),
+    };
+    if little_endian {
+        parse_makernote_sub::<LittleEndian>(
+            fields, index, data, note_data, note_offset)
+    } else {
+        parse_makernote_sub::<BigEndian>(
+            fields, index, data, note_data, note_offset)
+    }
+}
+
+fn parse_makernote_sub<'a, E>(fields: &mut Vec<Field<'a>>,
+                              index: &mut HashMap<(Tag, In), usize>,
+                              data: &'a [u8], note_data: &[u8],
+                              note_offset: usize)
+                              -> Result<(), Error> where E: Endian {
+    if note_data.starts_with(MAKERNOTE_OLYMPUS_SIG) {
+        // Layout (a): a plain IFD right after the signature and a
+        // 2-byte version; its value offsets are relative to the TIFF
+        // header, exactly like any other IFD.
+        let ifd_offset = note_offset + MAKERNOTE_OLYMPUS_SIG.len() + 2;
+        parse_makernote_ifd::<E>(fields, index, data, ifd_offset, 0)
+    } else if note_data.starts_with(MAKERNOTE_NIKON_SIG) {
+        // Layout (b): the signature and a 2-byte version are followed
+        // by a miniature TIFF header (its own byte-order mark and IFD
+        // offset); this inner IFD's value offsets are relative to the
+        // start of that header, not the outer TIFF header.
+        let tiff_header = note_offset + MAKERNOTE_NIKON_SIG.len() + 2;
+        if data.len() < tiff_header + 8 {
+            return Ok(());
+        }
+        let ifd_offset =
+            tiff_header + E::loadu32(data, tiff_header + 4) as usize;
+        parse_makernote_ifd::<E>(fields, index, data, ifd_offset, tiff_header)
+    } else {
+        // Unrecognized signature; leave the MakerNote field untouched.
+        Ok(())
+    }
+}
+
+// Parses a single maker-specific IFD.  Unlike `parse_ifd`, out-of-line
+// values are relative to `value_base`, there are no sub-IFD pointers
+// to recurse into, and the next-IFD offset is ignored.
+fn parse_makernote_ifd<'a, E>(fields: &mut Vec<Field<'a>>,
+                              index: &mut HashMap<(Tag, In), usize>,
+                              data: &'a [u8], offset: usize,
+                              value_base: usize)
+                              -> Result<(), Error> where E: Endian {
+    if data.len() < offset || data.len() - offset < 2 {
+        return Err(Error::InvalidFormat("Truncated MakerNote IFD count"));
+    }
+    let count = E::loadu16(data, offset) as usize;
+    if data.len() - offset - 2 < count * 12 {
+        return Err(Error::InvalidFormat("Truncated MakerNote IFD"));
+    }
+    for i in 0..count {
+        let tag_num = E::loadu16(data, offset + 2 + i * 12);
+        let typ = E::loadu16(data, offset + 2 + i * 12 + 2);
+        let cnt = E::loadu32(data, offset + 2 + i * 12 + 4) as usize;
+        let valofs_at = offset + 2 + i * 12 + 8;
+        let (unitlen, parser) = get_type_info::<E>(typ);
+        let vallen = try!(unitlen.checked_mul(cnt).ok_or(
+            Error::InvalidFormat("Invalid entry count")));
+        let val;
+        if unitlen == 0 {
+            val = Value::Unknown(typ, cnt as u32, valofs_at as u32);
+        } else if vallen <= INLINE_VALUE_MAX_LEN {
+            val = parser(data, valofs_at, cnt);
+        } else {
+            let ofs = value_base + E::loadu32(data, valofs_at) as usize;
+            if data.len() < ofs || data.len() - ofs < vallen {
+                return Err(Error::InvalidFormat("Truncated MakerNote value"));
+            }
+            val = parser(data, ofs, cnt);
+        }
+        // `Context::MakerNote` is a vendor-specific namespace, distinct
+        // from the standard Tiff/Exif/Gps/Interop contexts.
+        let tag = Tag(Context::MakerNote, tag_num);
+        // First-wins, matching the same duplicate-tag rule as parse_ifd.
+        index.entry((tag, In::PRIMARY)).or_insert(fields.len());
+        fields.push(Field { tag: tag, ifd_num: In::PRIMARY, value: val });
+    }
+    Ok(())
 }
 
 pub fn is_tiff(buf: &[u8]) -> bool {
     buf.starts_with(&TIFF_BE_SIG) || buf.starts_with(&TIFF_LE_SIG)
 }
 
+/// Returns the embedded thumbnail image, if any, as a slice of `data`.
+///
+/// `fields` must be the fields of the `Exif` that `parse_exif` returned
+/// for `data` (see `Exif::fields`).
+/// Prefers the `JPEGInterchangeFormat`/`JPEGInterchangeFormatLength`
+/// pair in the thumbnail (1st) IFD; otherwise falls back to
+/// `StripOffsets`/`StripByteCounts`, provided the strips tile a single
+/// contiguous region (non-contiguous strips cannot be returned as one
+/// borrowed slice).  Returns
+/// `None` if no thumbnail tags are present or the data is inconsistent.
+pub fn thumbnail<'a>(data: &'a [u8], fields: &[Field]) -> Option<&'a [u8]> {
+    let thumb: Vec<&Field> =
+        fields.iter().filter(|f| f.ifd_num == In::THUMBNAIL).collect();
+    let get = |tag: Tag| thumb.iter().find(|f| f.tag == tag).map(|f| &f.value);
+
+    if let (Some(jpeg_ofs), Some(jpeg_len)) =
+            (get(tag::JPEGInterchangeFormat),
+             get(tag::JPEGInterchangeFormatLength)) {
+        let ofs = match jpeg_ofs.get_uint(0) {
+            Some(v) => v as usize,
+            None => return None,
+        };
+        let len = match jpeg_len.get_uint(0) {
+            Some(v) => v as usize,
+            None => return None,
+        };
+        return slice_checked(data, ofs, len);
+    }
+
+    let offsets = match get(tag::StripOffsets) {
+        Some(v) => v,
+        None => return None,
+    };
+    let counts = match get(tag::StripByteCounts) {
+        Some(v) => v,
+        None => return None,
+    };
+    let mut strips = Vec::new();
+    let mut i = 0;
+    while let Some(ofs) = offsets.get_uint(i) {
+        let len = match counts.get_uint(i) {
+            Some(v) => v,
+            None => return None,
+        };
+        strips.push((ofs as usize, len as usize));
+        i += 1;
+    }
+    if strips.is_empty() {
+        return None;
+    }
+    strips.sort_by_key(|&(ofs, _)| ofs);
+    let (first_ofs, _) = strips[0];
+    let mut end = first_ofs;
+    for &(ofs, len) in &strips {
+        if ofs != end {
+            // The strips are not contiguous; this function cannot
+            // represent the thumbnail as a single borrowed slice.
+            return None;
+        }
+        end = match end.checked_add(len) {
+            Some(v) => v,
+            None => return None,
+        };
+    }
+    slice_checked(data, first_ofs, end - first_ofs)
+}
+
+// Returns `data[offset..offset + len]`, or `None` if it would be out of
+// bounds.
+fn slice_checked(data: &[u8], offset: usize, len: usize) -> Option<&[u8]> {
+    if data.len() < offset || data.len() - offset < len {
+        return None;
+    }
+    Some(&data[offset..offset + len])
+}
+
+// TIFF type IDs [EXIF23 4.6.2].
+const TYPE_BYTE: u16 = 1;
+const TYPE_ASCII: u16 = 2;
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+const TYPE_RATIONAL: u16 = 5;
+const TYPE_SBYTE: u16 = 6;
+const TYPE_UNDEFINED: u16 = 7;
+const TYPE_SSHORT: u16 = 8;
+const TYPE_SLONG: u16 = 9;
+const TYPE_SRATIONAL: u16 = 10;
+const TYPE_FLOAT: u16 = 11;
+const TYPE_DOUBLE: u16 = 12;
+
+// The maximum byte length of a value that is embedded directly in the
+// "value offset" element of an entry instead of an out-of-line data
+// area [EXIF23 4.6.2].
+const INLINE_VALUE_MAX_LEN: usize = 4;
+
+/// Writes a set of `Field`s back into a TIFF/Exif byte stream.
+///
+/// This is the write-side counterpart of `parse_exif`.  Fields are
+/// grouped by their `Context` into separate IFDs (the 0th, Exif, GPS,
+/// and Interop IFDs, plus every further `In` number present among the
+/// `Context::Tiff` fields, chained after the 0th IFD in order), the
+/// synthetic `ExifIFDPointer`, `GPSInfoIFDPointer`, and
+/// `InteropIFDPointer` entries are generated with the correct offsets,
+/// and values too large to embed inline are appended to a trailing
+/// data area.
+///
+/// # Examples
+/// ```
+/// use exif::{Field, Writer};
+/// let fields: Vec<Field> = Vec::new();
+/// let tiff = Writer::new(&fields).write(true).unwrap();
+/// assert!(exif::is_tiff(&tiff));
+/// ```
+pub struct Writer<'a> {
+    fields: &'a [Field<'a>],
+}
+
+impl<'a> Writer<'a> {
+    /// Creates a writer for the given fields.
+    pub fn new(fields: &'a [Field<'a>]) -> Writer<'a> {
+        Writer { fields: fields }
+    }
+
+    /// Serializes the fields into a TIFF stream using the given byte
+    /// order (`true` for little endian, `false` for big endian).
+    pub fn write(&self, little_endian: bool) -> Result<Vec<u8>, Error> {
+        if little_endian {
+            self.write_sub::<LittleEndian>(TIFF_LE_SIG, true)
+        } else {
+            self.write_sub::<BigEndian>(TIFF_BE_SIG, false)
+        }
+    }
+
+    fn write_sub<E>(&self, sig: [u8; 4], little_endian: bool)
+                    -> Result<Vec<u8>, Error> where E: Endian {
+        let group = |ctx: Context, ifd_num: In| -> Vec<&Field> {
+            self.fields.iter()
+                .filter(|f| f.tag.0 == ctx && f.ifd_num == ifd_num)
+                .collect()
+        };
+        let primary = group(Context::Tiff, In::PRIMARY);
+        let exif = group(Context::Exif, In::PRIMARY);
+        let gps = group(Context::Gps, In::PRIMARY);
+        let interop = group(Context::Interop, In::PRIMARY);
+        let mut chained_nums: Vec<u16> = self.fields.iter()
+            .filter(|f| f.tag.0 == Context::Tiff && f.ifd_num != In::PRIMARY)
+            .map(|f| f.ifd_num.0)
+            .collect();
+        chained_nums.sort();
+        chained_nums.dedup();
+
+        // Context::MakerNote fields are a derived view of a MakerNote
+        // field's raw bytes (see `parse_makernote`); the raw bytes are
+        // still present as an ordinary Context::Exif Undefined field
+        // and get serialized as such, so MakerNote fields themselves
+        // are intentionally not re-encoded here.  Anything else this
+        // writer has no IFD slot for is a real gap, so reject it
+        // instead of silently dropping it.
+        let accounted = primary.len() + exif.len() + gps.len() +
+            interop.len() +
+            chained_nums.iter().map(|&n| group(Context::Tiff, In(n)).len())
+                .sum::<usize>();
+        let makernote = self.fields.iter()
+            .filter(|f| f.tag.0 == Context::MakerNote).count();
+        if accounted + makernote != self.fields.len() {
+            return Err(Error::InvalidFormat(
+                "Writer: a field's Context/ifd_num has no corresponding IFD"));
+        }
+
+        // Fields belonging to a sub-IFD need a synthetic pointer entry
+        // in the IFD that, per [EXIF23], is supposed to hold it: the
+        // Exif and GPS pointers live in the 0th IFD, but the Interop
+        // pointer lives in the Exif IFD, not the 0th IFD, so it nests
+        // one level deeper.  A pointer's value is patched in once the
+        // pointed-to IFD has actually been laid out.
+        let mut sub_ifds: Vec<(Tag, Vec<&Field>)> = Vec::new();
+        if !exif.is_empty() || !interop.is_empty() {
+            sub_ifds.push((tag::ExifIFDPointer, exif));
+        }
+        if !gps.is_empty() {
+            sub_ifds.push((tag::GPSInfoIFDPointer, gps));
+        }
+        let extra_pointers: Vec<Tag> =
+            sub_ifds.iter().map(|&(t, _)| t).collect();
+
+        let mut buf = vec![0u8; 8];
+        buf[0..4].copy_from_slice(&sig);
+        E::writeu32(&mut buf, 4, 8);
+
+        let (patch_positions, next_ifd_patch) = try!(self.write_ifd::<E>(
+            &mut buf, &primary, &extra_pointers, little_endian));
+
+        for (patch_tag, patch_pos) in patch_positions {
+            let group_fields = &sub_ifds.iter()
+                .find(|&&(t, _)| t == patch_tag)
+                .expect("a patch position is only emitted for a tag in \
+                         extra_pointers, which is derived from sub_ifds").1;
+            let child_offset = buf.len();
+            if patch_tag == tag::ExifIFDPointer && !interop.is_empty() {
+                let (exif_patch_positions, _) = try!(self.write_ifd::<E>(
+                    &mut buf, group_fields, &[tag::InteropIFDPointer],
+                    little_endian));
+                E::writeu32(&mut buf, patch_pos, child_offset as u32);
+                let (_, interop_patch_pos) = exif_patch_positions[0];
+                let interop_offset = buf.len();
+                try!(self.write_ifd::<E>(&mut buf, &interop, &[],
+                                          little_endian));
+                E::writeu32(&mut buf, interop_patch_pos,
+                            interop_offset as u32);
+            } else {
+                try!(self.write_ifd::<E>(&mut buf, group_fields, &[],
+                                          little_endian));
+                E::writeu32(&mut buf, patch_pos, child_offset as u32);
+            }
+        }
+
+        // Chain every further top-level IFD (thumbnail and beyond) that
+        // holds at least one Context::Tiff field, in ascending `In`
+        // order, mirroring the chain `parse_ifd_chain` follows on read.
+        let mut next_patch_pos = next_ifd_patch;
+        for ifd_num in chained_nums {
+            let fields = group(Context::Tiff, In(ifd_num));
+            let ifd_offset = buf.len();
+            let (_, this_next_patch) = try!(self.write_ifd::<E>(
+                &mut buf, &fields, &[], little_endian));
+            E::writeu32(&mut buf, next_patch_pos, ifd_offset as u32);
+            next_patch_pos = this_next_patch;
+        }
+
+        Ok(buf)
+    }
+
+    // Appends one IFD (its entry table, followed by the out-of-line
+    // data area for entries whose value does not fit inline) to `buf`.
+    // Returns the buffer position of each `extra_pointers` entry's
+    // 4-byte value, tagged so the caller can patch in the pointed-to
+    // IFD's offset once it is known, and the position of this IFD's
+    // next-IFD-offset field for chaining.
+    fn write_ifd<E>(&self, buf: &mut Vec<u8>, fields: &[&Field],
+                    extra_pointers: &[Tag], little_endian: bool)
+                    -> Result<(Vec<(Tag, usize)>, usize), Error>
+                    where E: Endian {
+        enum Item<'f> { Real(&'f Field<'f>), Pointer(Tag) }
+
+        let mut items: Vec<(u16, Item)> = fields.iter()
+            .map(|f| (f.tag.1, Item::Real(*f)))
+            .collect();
+        for &ptr_tag in extra_pointers {
+            items.push((ptr_tag.1, Item::Pointer(ptr_tag)));
+        }
+        // Entries must appear in ascending tag order [EXIF23 4.6.2].
+        items.sort_by_key(|&(t, _)| t);
+
+        let count = items.len();
+        let ifd_start = buf.len();
+        let header_len = 2 + count * 12 + 4;
+        buf.extend(vec![0u8; header_len]);
+        E::writeu16(buf, ifd_start, count as u16);
+
+        let mut data_ofs = ifd_start + header_len;
+        let mut patch_positions = Vec::new();
+        for (i, &(tag_num, ref item)) in items.iter().enumerate() {
+            let entry_ofs = ifd_start + 2 + i * 12;
+            E::writeu16(buf, entry_ofs, tag_num);
+            match *item {
+                Item::Real(f) => {
+                    let (typ, cnt, bytes) =
+                        try!(encode_value::<E>(&f.value, little_endian));
+                    E::writeu16(buf, entry_ofs + 2, typ);
+                    E::writeu32(buf, entry_ofs + 4, cnt);
+                    if bytes.len() <= INLINE_VALUE_MAX_LEN {
+                        buf[entry_ofs + 8..entry_ofs + 8 + bytes.len()]
+                            .copy_from_slice(&bytes);
+                    } else {
+                        // Out-of-line values start on an even offset
+                        // [EXIF23 4.6.2].
+                        if data_ofs % 2 != 0 {
+                            buf.push(0);
+                            data_ofs += 1;
+                        }
+                        E::writeu32(buf, entry_ofs + 8, data_ofs as u32);
+                        buf.extend_from_slice(&bytes);
+                        data_ofs += bytes.len();
+                    }
+                }
+                Item::Pointer(ptr_tag) => {
+                    E::writeu16(buf, entry_ofs + 2, TYPE_LONG);
+                    E::writeu32(buf, entry_ofs + 4, 1);
+                    patch_positions.push((ptr_tag, entry_ofs + 8));
+                }
+            }
+        }
+
+        let next_ifd_ofs = ifd_start + 2 + count * 12;
+        Ok((patch_positions, next_ifd_ofs))
+    }
+}
+
+// Encodes a field value into (type, count, bytes) ready to be placed
+// either inline or in an IFD's out-of-line data area.
+fn encode_value<E>(value: &Value, little_endian: bool)
+                    -> Result<(u16, u32, Vec<u8>), Error> where E: Endian {
+    match *value {
+        Value::Byte(ref v) => Ok((TYPE_BYTE, v.len() as u32, v.clone())),
+        Value::Ascii(ref v) => {
+            let mut bytes = Vec::new();
+            for s in v {
+                bytes.extend_from_slice(s);
+                bytes.push(0);
+            }
+            Ok((TYPE_ASCII, bytes.len() as u32, bytes))
+        },
+        Value::Short(ref v) => {
+            let mut bytes = vec![0u8; v.len() * 2];
+            for (i, &x) in v.iter().enumerate() {
+                E::writeu16(&mut bytes, i * 2, x);
+            }
+            Ok((TYPE_SHORT, v.len() as u32, bytes))
+        },
+        Value::Long(ref v) => {
+            let mut bytes = vec![0u8; v.len() * 4];
+            for (i, &x) in v.iter().enumerate() {
+                E::writeu32(&mut bytes, i * 4, x);
+            }
+            Ok((TYPE_LONG, v.len() as u32, bytes))
+        },
+        Value::Rational(ref v) => {
+            let mut bytes = vec![0u8; v.len() * 8];
+            for (i, r) in v.iter().enumerate() {
+                E::writeu32(&mut bytes, i * 8, r.num);
+                E::writeu32(&mut bytes, i * 8 + 4, r.denom);
+            }
+            Ok((TYPE_RATIONAL, v.len() as u32, bytes))
+        },
+        Value::SByte(ref v) =>
+            Ok((TYPE_SBYTE, v.len() as u32,
+                v.iter().map(|&x| x as u8).collect())),
+        Value::Undefined(data, _) =>
+            Ok((TYPE_UNDEFINED, data.len() as u32, data.to_vec())),
+        Value::SShort(ref v) => {
+            let mut bytes = vec![0u8; v.len() * 2];
+            for (i, &x) in v.iter().enumerate() {
+                E::writeu16(&mut bytes, i * 2, x as u16);
+            }
+            Ok((TYPE_SSHORT, v.len() as u32, bytes))
+        },
+        Value::SLong(ref v) => {
+            let mut bytes = vec![0u8; v.len() * 4];
+            for (i, &x) in v.iter().enumerate() {
+                E::writeu32(&mut bytes, i * 4, x as u32);
+            }
+            Ok((TYPE_SLONG, v.len() as u32, bytes))
+        },
+        Value::SRational(ref v) => {
+            let mut bytes = vec![0u8; v.len() * 8];
+            for (i, r) in v.iter().enumerate() {
+                E::writeu32(&mut bytes, i * 8, r.num as u32);
+                E::writeu32(&mut bytes, i * 8 + 4, r.denom as u32);
+            }
+            Ok((TYPE_SRATIONAL, v.len() as u32, bytes))
+        },
+        Value::Float(ref v) => {
+            let mut bytes = vec![0u8; v.len() * 4];
+            for (i, &x) in v.iter().enumerate() {
+                E::writeu32(&mut bytes, i * 4, x.to_bits());
+            }
+            Ok((TYPE_FLOAT, v.len() as u32, bytes))
+        },
+        Value::Double(ref v) => {
+            let mut bytes = vec![0u8; v.len() * 8];
+            for (i, &x) in v.iter().enumerate() {
+                let bits = x.to_bits();
+                let (hi, lo) = ((bits >> 32) as u32, bits as u32);
+                if little_endian {
+                    E::writeu32(&mut bytes, i * 8, lo);
+                    E::writeu32(&mut bytes, i * 8 + 4, hi);
+                } else {
+                    E::writeu32(&mut bytes, i * 8, hi);
+                    E::writeu32(&mut bytes, i * 8 + 4, lo);
+                }
+            }
+            Ok((TYPE_DOUBLE, v.len() as u32, bytes))
+        },
+        Value::Unknown(..) => Err(Error::InvalidFormat(
+            "Cannot serialize a value of unknown type")),
+    }
+}
+
 /// A struct used to parse a DateTime field.
 ///
 /// # Examples
@@ -177,6 +787,12 @@ pub struct DateTime {
     pub hour: u8,
     pub minute: u8,
     pub second: u8,
+    /// The sub-second part of the time, in nanoseconds, parsed from a
+    /// `SubSecTime*` field.  `None` if the field is absent.
+    pub nanosecond: Option<u32>,
+    /// The time zone offset in minutes east of UTC, parsed from an
+    /// `OffsetTime*` field.  `None` if the field is absent.
+    pub offset: Option<i16>,
 }
 
 impl DateTime {
@@ -198,15 +814,82 @@ impl DateTime {
             hour: try!(atou16(&data[11..13])) as u8,
             minute: try!(atou16(&data[14..16])) as u8,
             second: try!(atou16(&data[17..19])) as u8,
+            nanosecond: None,
+            offset: None,
         })
     }
+
+    /// Parse the ASCII data of a `SubSecTime*` field and fill in
+    /// `self.nanosecond`.  Up to 9 leading digits are taken and scaled
+    /// to nanoseconds by right-padding with zeros (e.g., "07" becomes
+    /// 70_000_000); any digits beyond the 9th are ignored.  Trailing
+    /// spaces and NULs, as commonly found in this field, are stripped
+    /// first.
+    pub fn parse_subsec(&mut self, data: &[u8]) -> Result<(), Error> {
+        let data = trim_end_spc_nul(data);
+        if data.is_empty() {
+            return Err(Error::InvalidFormat("SubSecTime is empty"));
+        }
+        let mut digits = [b'0'; 9];
+        let n = cmp::min(data.len(), digits.len());
+        digits[..n].copy_from_slice(&data[..n]);
+        self.nanosecond = Some(try!(atou16plus(&digits)));
+        Ok(())
+    }
+
+    /// Parse the ASCII data of an `OffsetTime*` field (e.g., "+09:00"
+    /// or "-05:30") and fill in `self.offset` with the number of
+    /// minutes east of UTC.
+    pub fn parse_offset(&mut self, data: &[u8]) -> Result<(), Error> {
+        let data = trim_end_spc_nul(data);
+        if data.len() != 6 ||
+           !((data[0] == b'+' || data[0] == b'-') && data[3] == b':') {
+            return Err(Error::InvalidFormat("Invalid OffsetTime delimiter"));
+        }
+        let hour = try!(atou16(&data[1..3])) as i16;
+        let minute = try!(atou16(&data[4..6])) as i16;
+        let offset = hour * 60 + minute;
+        self.offset = Some(if data[0] == b'-' { -offset } else { offset });
+        Ok(())
+    }
+}
+
+// Strip the trailing spaces and NULs that commonly pad fixed-length
+// ASCII Exif string fields.
+fn trim_end_spc_nul(data: &[u8]) -> &[u8] {
+    let n = data.iter().rposition(|&b| b != b' ' && b != 0)
+        .map_or(0, |i| i + 1);
+    &data[..n]
+}
+
+// Like `atou16`, but returns the 9-digit fractional-seconds string
+// scaled to nanoseconds (which does not fit in a u16, despite the name
+// of the helper it mirrors).
+fn atou16plus(digits: &[u8; 9]) -> Result<u32, Error> {
+    let mut value: u32 = 0;
+    for &b in digits {
+        if !(b'0' <= b && b <= b'9') {
+            return Err(Error::InvalidFormat("Invalid SubSecTime digit"));
+        }
+        value = value * 10 + (b - b'0') as u32;
+    }
+    Ok(value)
 }
 
 impl fmt::Display for DateTime {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
-               self.year, self.month, self.day,
-               self.hour, self.minute, self.second)
+        try!(write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                     self.year, self.month, self.day,
+                     self.hour, self.minute, self.second));
+        if let Some(ns) = self.nanosecond {
+            try!(write!(f, ".{:09}", ns));
+        }
+        if let Some(offset) = self.offset {
+            let (sign, offset) = if offset < 0 { ('-', -offset) }
+                                  else { ('+', offset) };
+            try!(write!(f, "{}{:02}:{:02}", sign, offset / 60, offset % 60));
+        }
+        Ok(())
     }
 }
 
@@ -214,22 +897,257 @@ impl fmt::Display for DateTime {
 mod tests {
     use super::*;
 
-    // Before the error is returned, the IFD is parsed twice as the
-    // 0th and 1st IFDs.
+    // The "next IFD" offset points back to the 0th IFD itself, which
+    // must be rejected instead of looping forever.
     #[test]
     fn inf_loop_by_next() {
         let data = b"MM\0\x2a\0\0\0\x08\
                      \0\x01\x01\0\0\x03\0\0\0\x01\0\x14\0\0\0\0\0\x08";
         assert_err_pat!(parse_exif(data),
-                        Error::InvalidFormat("Unexpected next IFD"));
+                        Error::InvalidFormat("IFD loop"));
     }
 
     #[test]
     fn unknown_field() {
         let data = b"MM\0\x2a\0\0\0\x08\
                      \0\x01\x01\0\xff\xff\0\0\0\x01\0\x14\0\0\0\0\0\0";
-        let (v, _) = parse_exif(data).unwrap();
-        assert_eq!(v.len(), 1);
-        assert_pat!(v[0].value, Value::Unknown(0xffff, 1, 0x12));
+        let exif = parse_exif(data).unwrap();
+        assert_eq!(exif.fields().len(), 1);
+        assert_pat!(exif.fields()[0].value, Value::Unknown(0xffff, 1, 0x12));
+        assert_pat!(exif.get_field(exif.fields()[0].tag, In::PRIMARY)
+                    .unwrap().value, Value::Unknown(0xffff, 1, 0x12));
+    }
+
+    // A field in the 0th IFD and one in its Exif sub-IFD must both
+    // survive a Writer::write / parse_exif round trip, including the
+    // synthetic ExifIFDPointer Writer inserts to link them.
+    #[test]
+    fn write_read_round_trip() {
+        let fields = vec![
+            Field { tag: Tag(Context::Tiff, 0x0100), ifd_num: In::PRIMARY,
+                    value: Value::Short(vec![10]) },
+            Field { tag: Tag(Context::Exif, 0x9000), ifd_num: In::PRIMARY,
+                    value: Value::Undefined(b"0231", 0) },
+        ];
+        let buf = Writer::new(&fields).write(true).unwrap();
+        let exif = parse_exif(&buf).unwrap();
+        assert!(exif.little_endian());
+        match exif.get_field(Tag(Context::Tiff, 0x0100), In::PRIMARY)
+                  .unwrap().value {
+            Value::Short(ref v) => assert_eq!(v[0], 10),
+            ref other => panic!("unexpected value: {:?}", other),
+        }
+        match exif.get_field(Tag(Context::Exif, 0x9000), In::PRIMARY)
+                  .unwrap().value {
+            Value::Undefined(d, _) => assert_eq!(d, b"0231"),
+            ref other => panic!("unexpected value: {:?}", other),
+        }
+    }
+
+    // A field in the Interop IFD must survive a round trip too: its
+    // pointer is nested inside the Exif IFD, not the 0th IFD, so this
+    // exercises the write-side's second level of nesting as well as
+    // the read side's context-sensitive recognition of
+    // InteropIFDPointer.
+    #[test]
+    fn write_read_round_trip_interop() {
+        let fields = vec![
+            Field { tag: Tag(Context::Interop, 0x0001), ifd_num: In::PRIMARY,
+                    value: Value::Short(vec![7]) },
+        ];
+        let buf = Writer::new(&fields).write(true).unwrap();
+        let exif = parse_exif(&buf).unwrap();
+        match exif.get_field(Tag(Context::Interop, 0x0001), In::PRIMARY)
+                  .unwrap().value {
+            Value::Short(ref v) => assert_eq!(v[0], 7),
+            ref other => panic!("unexpected value: {:?}", other),
+        }
+    }
+
+    // parse_subsec and parse_offset fill in DateTime's optional fields,
+    // and Display must render them in the expected ISO-ish format.
+    #[test]
+    fn datetime_subsec_and_offset() {
+        let mut dt = DateTime::from_ascii(b"2016:05:04 03:02:01").unwrap();
+        dt.parse_subsec(b"07\0\0").unwrap();
+        dt.parse_offset(b"+09:00").unwrap();
+        assert_eq!(dt.nanosecond, Some(70_000_000));
+        assert_eq!(dt.offset, Some(9 * 60));
+        assert_eq!(format!("{}", dt), "2016-05-04 03:02:01.070000000+09:00");
+
+        let mut dt = DateTime::from_ascii(b"2016:05:04 03:02:01").unwrap();
+        dt.parse_offset(b"-05:30").unwrap();
+        assert_eq!(dt.offset, Some(-(5 * 60 + 30)));
+        assert_eq!(format!("{}", dt), "2016-05-04 03:02:01-05:30");
+    }
+
+    // A chain of three top-level IFDs (beyond just primary+thumbnail)
+    // must be fully followed, with each field tagged with its IFD's
+    // `In` number.
+    #[test]
+    fn three_ifd_chain() {
+        let data = b"MM\0*\0\0\0\x08\
+                     \0\x01\x01\0\0\x03\0\0\0\x01\0\x01\0\0\0\0\0\x1a\
+                     \0\x01\x01\0\0\x03\0\0\0\x01\0\x02\0\0\0\0\0,\
+                     \0\x01\x01\0\0\x03\0\0\0\x01\0\x03\0\0\0\0\0\0";
+        let exif = parse_exif(data).unwrap();
+        assert_eq!(exif.fields().len(), 3);
+        let tag = Tag(Context::Tiff, 0x0100);
+        for &(ifd, expected) in
+                [(In::PRIMARY, 1u16), (In::THUMBNAIL, 2), (In(2), 3)].iter() {
+            match exif.get_field(tag, ifd).unwrap().value {
+                Value::Short(ref v) => assert_eq!(v[0], expected),
+                ref other => panic!("unexpected value: {:?}", other),
+            }
+        }
+    }
+
+    // When an IFD has two entries with the same tag, `get_field` must
+    // agree with a linear scan over `fields()` and resolve to the
+    // first occurrence, not the last.
+    #[test]
+    fn duplicate_tag_first_wins() {
+        let data = b"MM\0\x2a\0\0\0\x08\
+                     \0\x02\
+                     \xff\xff\0\x03\0\0\0\x01\0\x05\0\0\
+                     \xff\xff\0\x03\0\0\0\x01\0\x09\0\0\
+                     \0\0\0\0";
+        let exif = parse_exif(data).unwrap();
+        assert_eq!(exif.fields().len(), 2);
+        let tag = exif.fields()[0].tag;
+        match exif.get_field(tag, In::PRIMARY).unwrap().value {
+            Value::Short(ref v) => assert_eq!(v[0], 5),
+            ref other => panic!("unexpected value: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn thumbnail_jpeg() {
+        let data = b"0123456789ABCDEF";
+        let fields = vec![
+            Field { tag: tag::JPEGInterchangeFormat, ifd_num: In::THUMBNAIL,
+                     value: Value::Long(vec![5]) },
+            Field { tag: tag::JPEGInterchangeFormatLength,
+                     ifd_num: In::THUMBNAIL, value: Value::Long(vec![4]) },
+        ];
+        assert_eq!(thumbnail(data, &fields), Some(&data[5..9]));
+    }
+
+    // Two strips that tile a contiguous region must be returned as one
+    // slice spanning both.
+    #[test]
+    fn thumbnail_strips_contiguous() {
+        let data = b"0123456789ABCDEF";
+        let fields = vec![
+            Field { tag: tag::StripOffsets, ifd_num: In::THUMBNAIL,
+                     value: Value::Long(vec![2, 6]) },
+            Field { tag: tag::StripByteCounts, ifd_num: In::THUMBNAIL,
+                     value: Value::Long(vec![4, 4]) },
+        ];
+        assert_eq!(thumbnail(data, &fields), Some(&data[2..10]));
+    }
+
+    // A gap between strips cannot be represented as a single borrowed
+    // slice, so it must be rejected rather than skipping the gap.
+    #[test]
+    fn thumbnail_strips_noncontiguous() {
+        let data = b"0123456789ABCDEF";
+        let fields = vec![
+            Field { tag: tag::StripOffsets, ifd_num: In::THUMBNAIL,
+                     value: Value::Long(vec![2, 8]) },
+            Field { tag: tag::StripByteCounts, ifd_num: In::THUMBNAIL,
+                     value: Value::Long(vec![4, 4]) },
+        ];
+        assert_eq!(thumbnail(data, &fields), None);
+    }
+
+    fn note_field(data: &[u8]) -> Field {
+        Field { tag: tag::MakerNote, ifd_num: In::PRIMARY,
+                value: Value::Undefined(data, 0) }
+    }
+
+    #[test]
+    fn makernote_olympus() {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"OLYMP\0");
+        data.extend_from_slice(&[0, 2]); // version
+        data.extend_from_slice(&[0, 1]); // IFD entry count
+        data.extend_from_slice(
+            &[0, 1, 0, 3, 0, 0, 0, 1, 0, 42, 0, 0]); // tag 1, SHORT, 42
+        data.extend_from_slice(&[0, 0, 0, 0]); // next IFD offset
+
+        let mut fields = vec![note_field(&data)];
+        let mut index = HashMap::new();
+        parse_makernote(&mut fields, &mut index, &data, false).unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[1].tag, Tag(Context::MakerNote, 1));
+        match fields[1].value {
+            Value::Short(ref v) => assert_eq!(v[0], 42),
+            ref other => panic!("unexpected value: {:?}", other),
+        }
+    }
+
+    // Exif::parse_makernote must be reachable from a parse_exif result
+    // obtained through the public API alone, without hand-building
+    // fields/index the way the free-function tests above do.
+    #[test]
+    fn exif_parse_makernote() {
+        let mut note = Vec::new();
+        note.extend_from_slice(b"OLYMP\0");
+        note.extend_from_slice(&[0, 2]); // version
+        note.extend_from_slice(&[0, 1]); // IFD entry count
+        note.extend_from_slice(
+            &[0, 1, 0, 3, 0, 0, 0, 1, 0, 42, 0, 0]); // tag 1, SHORT, 42
+        note.extend_from_slice(&[0, 0, 0, 0]); // next IFD offset
+
+        let fields = vec![
+            Field { tag: tag::MakerNote, ifd_num: In::PRIMARY,
+                    value: Value::Undefined(&note, 0) },
+        ];
+        let buf = Writer::new(&fields).write(false).unwrap();
+        let mut exif = parse_exif(&buf).unwrap();
+        exif.parse_makernote(&buf).unwrap();
+        match exif.get_field(Tag(Context::MakerNote, 1), In::PRIMARY)
+                  .unwrap().value {
+            Value::Short(ref v) => assert_eq!(v[0], 42),
+            ref other => panic!("unexpected value: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn makernote_nikon() {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"Nikon\0");
+        data.extend_from_slice(&[0, 2]); // version
+        let tiff_header = data.len();
+        data.extend_from_slice(b"MM"); // the inner header's own BOM
+        data.extend_from_slice(&[0, 42]); // forty two
+        data.extend_from_slice(&[0, 0, 0, 8]); // IFD offset, relative to
+                                                // tiff_header
+        assert_eq!(data.len(), tiff_header + 8);
+        data.extend_from_slice(&[0, 1]); // IFD entry count
+        data.extend_from_slice(
+            &[0, 1, 0, 3, 0, 0, 0, 1, 0, 42, 0, 0]); // tag 1, SHORT, 42
+        data.extend_from_slice(&[0, 0, 0, 0]); // next IFD offset
+
+        let mut fields = vec![note_field(&data)];
+        let mut index = HashMap::new();
+        parse_makernote(&mut fields, &mut index, &data, false).unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[1].tag, Tag(Context::MakerNote, 1));
+        match fields[1].value {
+            Value::Short(ref v) => assert_eq!(v[0], 42),
+            ref other => panic!("unexpected value: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn makernote_unrecognized_signature() {
+        let data =
+            b"XXXXX\0\0\0\0\x01\0\x01\0\x03\0\0\0\x01\0\x2a\0\0\0\0\0\0";
+        let mut fields = vec![note_field(data)];
+        let mut index = HashMap::new();
+        parse_makernote(&mut fields, &mut index, data, false).unwrap();
+        assert_eq!(fields.len(), 1);
     }
 }